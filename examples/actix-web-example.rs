@@ -10,14 +10,15 @@ use server_starter_listener::{listeners, ServerStarterListener};
 /// > curl -i localhost:8000/hello
 /// > kill -SIGHUP `cat /tmp/actix-web-example.pid` # hot deploy
 /// ```
-fn main() {
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "actix_web=info");
     env_logger::init();
 
     let builder = HttpServer::new(|| {
         App::new().service(web::resource("/hello").route(web::get().to(|| {
             log::info!("pid {:?}", std::process::id());
-            return HttpResponse::Ok();
+            HttpResponse::Ok()
         })))
     });
 
@@ -26,13 +27,10 @@ fn main() {
         .into_iter()
         .fold(builder, |builder, listener| {
             match listener {
-                ServerStarterListener::Tcp(listener) => builder.listen(listener).unwrap(),
-                ServerStarterListener::Uds(listener) => {
-                    // listen_uds required actix-web "uds" features
-                    builder.listen_uds(listener).unwrap()
-                }
+                ServerStarterListener::Tcp { listener, .. } => builder.listen(listener).unwrap(),
+                ServerStarterListener::Uds { listener, .. } => builder.listen_uds(listener).unwrap(),
             }
         });
 
-    builder.run().unwrap();
+    builder.run().await
 }
@@ -0,0 +1,67 @@
+//! Helpers for performing a zero-downtime restart with `start_server`.
+//!
+//! `start_server` hot-deploys by starting a new worker, waiting for it to bind its listeners,
+//! and then sending `SIGTERM` to the old worker so it can drain in-flight connections. This
+//! module gives the old worker something to await on that signal, and exposes the
+//! `SERVER_STARTER_GENERATION` env var so logs/metrics can tag which generation served a
+//! request during the overlap.
+
+#[cfg(not(feature = "tokio"))]
+compile_error!("the hot_deploy feature requires the tokio feature to be enabled");
+
+const SERVER_STARTER_GENERATION_ENV: &str = "SERVER_STARTER_GENERATION";
+
+///
+/// The Server::Starter generation number of the current worker, if set.
+///
+/// `start_server` increments this on every hot-deploy, so two workers can be running the same
+/// binary with different generations during the handoff.
+///
+pub fn generation() -> Option<u32> {
+    std::env::var(SERVER_STARTER_GENERATION_ENV)
+        .ok()
+        .and_then(|generation| generation.parse().ok())
+}
+
+///
+/// Waits for `start_server` to send `SIGTERM` as part of a hot-deploy.
+///
+/// Feed this into a server's graceful-shutdown hook, e.g. hyper's `with_graceful_shutdown` or
+/// actix's `Server::stop(true)`, so in-flight requests finish before the old worker exits.
+///
+/// # Panics
+///
+/// Panics if a `SIGTERM` handler cannot be installed.
+///
+pub async fn on_shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm =
+        signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    sigterm.recv().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generation;
+
+    #[test]
+    fn generation_without_env() {
+        std::env::remove_var("SERVER_STARTER_GENERATION");
+        assert_eq!(None, generation());
+    }
+
+    #[test]
+    fn generation_with_env() {
+        std::env::set_var("SERVER_STARTER_GENERATION", "2");
+        assert_eq!(Some(2), generation());
+        std::env::remove_var("SERVER_STARTER_GENERATION");
+    }
+
+    #[test]
+    fn generation_with_invalid_env() {
+        std::env::set_var("SERVER_STARTER_GENERATION", "not-a-number");
+        assert_eq!(None, generation());
+        std::env::remove_var("SERVER_STARTER_GENERATION");
+    }
+}
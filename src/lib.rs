@@ -8,12 +8,15 @@
 //! use actix_web::{HttpServer, App};
 //! use server_starter_listener::{listeners, ServerStarterListener};
 //!
-//! let listener = listeners().unwrap().pop().unwrap();
-//! match listener {
-//!   ServerStarterListener::Tcp(listener) => {
-//!     HttpServer::new(|| App::new()).listen(listener).unwrap().run().unwrap();
-//!   }
-//!   _ => unimplemented!(),
+//! #[actix_web::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let listener = listeners().unwrap().pop().unwrap();
+//!     match listener {
+//!         ServerStarterListener::Tcp { listener, .. } => {
+//!             HttpServer::new(|| App::new()).listen(listener).unwrap().run().await
+//!         }
+//!         _ => unimplemented!(),
+//!     }
 //! }
 //! ```
 //!
@@ -26,19 +29,36 @@
 //! Now you can do hot-deploy by send `SIGHUP` to `start_server` process.
 //! `start_server` share file descriptor to new process and send `SIGTERM` to old process.
 //!
+//! The `hot_deploy` feature adds the `hot_deploy` module, which helps the old process drain
+//! in-flight connections on that `SIGTERM` before exiting.
+//!
+
+// `failure`'s `Fail` derive expands into a non-local `impl`; allow it crate-wide rather than
+// at every error enum.
+#![allow(non_local_definitions)]
 
 #[macro_use]
 extern crate failure;
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "hot_deploy")]
+pub mod hot_deploy;
+
 use std::net::TcpListener;
+use std::os::linux::net::SocketAddrExt;
 use std::os::unix::io::{FromRawFd, RawFd};
 use std::os::unix::net::UnixListener;
 
 use regex::Regex;
 
 const SERVER_STARTER_PORT_ENV: &str = "SERVER_STARTER_PORT";
+const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+const LISTEN_PID_ENV: &str = "LISTEN_PID";
+const LISTEN_FDNAMES_ENV: &str = "LISTEN_FDNAMES";
+
+/// First fd handed out by systemd socket-activation (`SD_LISTEN_FDS_START`).
+const SD_LISTEN_FDS_START: RawFd = 3;
 
 lazy_static! {
     static ref HOST_PORT_REGEX: Regex = Regex::new("^[^:]+:\\d+$").unwrap();
@@ -50,19 +70,164 @@ lazy_static! {
 ///
 #[derive(Debug)]
 pub enum ServerStarterListener {
-    Tcp(TcpListener),
-    Uds(UnixListener),
+    Tcp {
+        listener: TcpListener,
+        /// The host parsed from the spec, e.g. `Some("127.0.0.1")` for `127.0.0.1:8080=3`.
+        /// `None` when the spec was a bare port, e.g. `8080=3`.
+        host: Option<String>,
+        port: u16,
+        /// The name assigned via systemd's `LISTEN_FDNAMES`, if any. Always `None` for
+        /// listeners parsed from `SERVER_STARTER_PORT`, which has no equivalent naming scheme.
+        name: Option<String>,
+    },
+    Uds {
+        listener: UnixListener,
+        /// The path parsed from the spec, or the bound address of a socket inherited from
+        /// systemd. For an abstract socket it is the name prefixed with a NUL byte. Empty when
+        /// the address could not be recovered (e.g. an unnamed socket inherited from systemd).
+        path: String,
+        /// `true` if the socket lives in the abstract namespace (leading NUL byte) rather
+        /// than being bound to a path on the filesystem.
+        is_abstract: bool,
+        /// The name assigned via systemd's `LISTEN_FDNAMES`, if any. Always `None` for
+        /// listeners parsed from `SERVER_STARTER_PORT`, which has no equivalent naming scheme.
+        name: Option<String>,
+    },
 }
 
 impl ServerStarterListener {
-    fn tcp(fd: RawFd) -> ServerStarterListener {
-        ServerStarterListener::Tcp(unsafe { TcpListener::from_raw_fd(fd) })
+    fn from_tcp(
+        listener: TcpListener,
+        host: Option<String>,
+        port: u16,
+        name: Option<String>,
+    ) -> ServerStarterListener {
+        ServerStarterListener::Tcp {
+            listener,
+            host,
+            port,
+            name,
+        }
+    }
+
+    fn from_uds(
+        listener: UnixListener,
+        path: String,
+        is_abstract: bool,
+        name: Option<String>,
+    ) -> std::io::Result<ServerStarterListener> {
+        Ok(ServerStarterListener::Uds {
+            listener,
+            path,
+            is_abstract,
+            name,
+        })
+    }
+
+    /// Wraps a file descriptor inherited via systemd socket-activation, probing its
+    /// socket family with `getsockopt(SO_DOMAIN)` to decide `Tcp` vs `Uds`.
+    ///
+    /// `name` is the corresponding entry from `LISTEN_FDNAMES`, if systemd supplied one.
+    fn from_systemd_fd(
+        fd: RawFd,
+        name: Option<String>,
+    ) -> Result<ServerStarterListener, ListenerError> {
+        let mut domain: libc::c_int = 0;
+        let mut domain_len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_DOMAIN,
+                &mut domain as *mut libc::c_int as *mut libc::c_void,
+                &mut domain_len,
+            )
+        };
+        if ret != 0 {
+            let err = std::io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(ListenerError::SystemdFdError(err));
+        }
+
+        match domain {
+            libc::AF_INET | libc::AF_INET6 => {
+                let listener = unsafe { TcpListener::from_raw_fd(fd) };
+                let port = listener
+                    .local_addr()
+                    .map_err(ListenerError::SystemdFdError)?
+                    .port();
+                Ok(ServerStarterListener::from_tcp(listener, None, port, name))
+            }
+            libc::AF_UNIX => {
+                let listener = unsafe { UnixListener::from_raw_fd(fd) };
+                let addr = listener
+                    .local_addr()
+                    .map_err(ListenerError::UnixListenerBindError)?;
+                let (path, is_abstract) = match addr.as_abstract_name() {
+                    Some(abstract_name) => (
+                        format!("\0{}", String::from_utf8_lossy(abstract_name)),
+                        true,
+                    ),
+                    None => (
+                        addr.as_pathname()
+                            .map(|p| p.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        false,
+                    ),
+                };
+                ServerStarterListener::from_uds(listener, path, is_abstract, name)
+                    .map_err(ListenerError::UnixListenerBindError)
+            }
+            _ => {
+                unsafe { libc::close(fd) };
+                Err(ListenerError::UnsupportedSocketDomain(fd, domain))
+            }
+        }
+    }
+}
+
+///
+/// A list of [ServerStarterListener] with lookups by the spec that produced each one.
+///
+#[derive(Debug)]
+pub struct Listeners(Vec<ServerStarterListener>);
+
+impl Listeners {
+    /// Finds the tcp listener bound to the given port, if any.
+    pub fn get_by_port(&self, port: u16) -> Option<&ServerStarterListener> {
+        self.0.iter().find(|listener| {
+            matches!(listener, ServerStarterListener::Tcp { port: p, .. } if *p == port)
+        })
+    }
+
+    /// Finds the unix domain socket listener bound to the given path, if any.
+    pub fn get_by_path(&self, path: &str) -> Option<&ServerStarterListener> {
+        self.0.iter().find(|listener| {
+            matches!(listener, ServerStarterListener::Uds { path: p, .. } if p == path)
+        })
+    }
+}
+
+impl std::ops::Deref for Listeners {
+    type Target = Vec<ServerStarterListener>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for Listeners {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
     }
+}
 
-    fn uds(fd: RawFd) -> std::io::Result<ServerStarterListener> {
-        Ok(ServerStarterListener::Uds(unsafe {
-            UnixListener::from_raw_fd(fd)
-        }))
+impl IntoIterator for Listeners {
+    type Item = ServerStarterListener;
+    type IntoIter = std::vec::IntoIter<ServerStarterListener>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
     }
 }
 
@@ -77,6 +242,81 @@ pub enum ListenerError {
     InvalidServerStarterPortSpec(String),
     #[fail(display = "failed to bind uds: {}", _0)]
     UnixListenerBindError(#[fail(cause)] std::io::Error),
+    #[fail(display = "failed to inspect systemd socket-activation fd: {}", _0)]
+    SystemdFdError(#[fail(cause)] std::io::Error),
+    #[fail(
+        display = "LISTEN_PID ({}) does not match current process id ({})",
+        _0, _1
+    )]
+    ListenPidMismatch(i32, u32),
+    #[fail(display = "invalid abstract socket spec: {}", _0)]
+    InvalidAbstractSocketSpec(String),
+    #[fail(
+        display = "systemd fd {} has unsupported socket domain {}",
+        _0, _1
+    )]
+    UnsupportedSocketDomain(RawFd, libc::c_int),
+    #[cfg(feature = "tokio")]
+    #[fail(display = "failed to convert listener for tokio: {}", _0)]
+    TokioConversionError(#[fail(cause)] std::io::Error),
+}
+
+/// Unescapes a socket spec encoded in Rust's `escape_default` form (as produced by sccache),
+/// turning sequences like `\x00`, `\t`, `\r`, `\n`, `\'`, `\"` and `\\` back into raw bytes.
+/// This lets abstract-namespace sockets (which start with a NUL byte, and may contain other
+/// non-printable bytes) be passed through the text-based `SERVER_STARTER_PORT` env var.
+fn unescape_abstract_socket_spec(spec: &str) -> Result<Vec<u8>, ListenerError> {
+    let bytes = spec.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            unescaped.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        match bytes.get(i + 1) {
+            Some(b'x') => {
+                let hex = spec
+                    .get(i + 2..i + 4)
+                    .ok_or_else(|| ListenerError::InvalidAbstractSocketSpec(spec.into()))?;
+                let byte = u8::from_str_radix(hex, 16)
+                    .map_err(|_| ListenerError::InvalidAbstractSocketSpec(spec.into()))?;
+                unescaped.push(byte);
+                i += 4;
+            }
+            Some(b't') => {
+                unescaped.push(b'\t');
+                i += 2;
+            }
+            Some(b'r') => {
+                unescaped.push(b'\r');
+                i += 2;
+            }
+            Some(b'n') => {
+                unescaped.push(b'\n');
+                i += 2;
+            }
+            Some(&escaped @ (b'\\' | b'\'' | b'"')) => {
+                unescaped.push(escaped);
+                i += 2;
+            }
+            _ => return Err(ListenerError::InvalidAbstractSocketSpec(spec.into())),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Splits `LISTEN_FDNAMES` into the per-fd names systemd assigns, in fd order. Returns an
+/// empty `Vec` when `raw` is `None` or empty, so listeners default to unnamed.
+fn parse_fd_names(raw: Option<&str>) -> Vec<Option<String>> {
+    match raw {
+        Some(names) if !names.is_empty() => {
+            names.split(':').map(|name| Some(name.into())).collect()
+        }
+        _ => vec![],
+    }
 }
 
 ///
@@ -88,7 +328,7 @@ pub enum ListenerError {
 ///
 /// Returns as `ListenerError` if `SERVER_STARTER_PORT` env var is not found or invalid format.
 ///
-pub fn listeners() -> Result<Vec<ServerStarterListener>, ListenerError> {
+pub fn listeners() -> Result<Listeners, ListenerError> {
     let specs = match std::env::var(SERVER_STARTER_PORT_ENV) {
         Ok(specs) => specs,
         Err(_) => return Err(ListenerError::ServerStarterPortEnvNotFound),
@@ -108,88 +348,531 @@ pub fn listeners() -> Result<Vec<ServerStarterListener>, ListenerError> {
             Err(_) => return Err(ListenerError::InvalidServerStarterPortSpec(spec.into())),
         };
 
-        if let Some(_) = HOST_PORT_REGEX.find(left) {
-            results.push(ServerStarterListener::tcp(fd));
-        } else if let Some(_) = PORT_REGEX.find(left) {
-            results.push(ServerStarterListener::tcp(fd));
-        } else {
-            let uds_listener = match ServerStarterListener::uds(fd) {
-                Ok(uds_listener) => uds_listener,
-                Err(e) => return Err(ListenerError::UnixListenerBindError(e)),
+        if HOST_PORT_REGEX.find(left).is_some() {
+            let colon = left.rfind(':').unwrap();
+            let (host, port) = (&left[..colon], &left[colon + 1..]);
+            let listener = unsafe { TcpListener::from_raw_fd(fd) };
+            let port: u16 = match port.parse() {
+                Ok(port) => port,
+                Err(_) => return Err(ListenerError::InvalidServerStarterPortSpec(spec.into())),
             };
+            results.push(ServerStarterListener::from_tcp(
+                listener,
+                Some(host.into()),
+                port,
+                None,
+            ));
+        } else if PORT_REGEX.find(left).is_some() {
+            let listener = unsafe { TcpListener::from_raw_fd(fd) };
+            let port: u16 = match left.parse() {
+                Ok(port) => port,
+                Err(_) => return Err(ListenerError::InvalidServerStarterPortSpec(spec.into())),
+            };
+            results.push(ServerStarterListener::from_tcp(listener, None, port, None));
+        } else {
+            let listener = unsafe { UnixListener::from_raw_fd(fd) };
+            let unescaped = unescape_abstract_socket_spec(left)?;
+            let is_abstract = unescaped.first().is_some_and(|&b| b == 0);
+            let path = String::from_utf8_lossy(&unescaped).into_owned();
+            let uds_listener =
+                match ServerStarterListener::from_uds(listener, path, is_abstract, None) {
+                    Ok(uds_listener) => uds_listener,
+                    Err(e) => return Err(ListenerError::UnixListenerBindError(e)),
+                };
             results.push(uds_listener);
         }
     }
-    Ok(results)
+    Ok(Listeners(results))
+}
+
+///
+/// Get listeners passed via systemd's socket-activation protocol.
+///
+/// Reads `LISTEN_FDS` / `LISTEN_PID` (and optionally colon-separated `LISTEN_FDNAMES`) as set
+/// by systemd for a `.socket` unit with `Accept=no`, and wraps each inherited file descriptor
+/// starting at fd 3 (`SD_LISTEN_FDS_START`). This lets the same binary run under either
+/// Server::Starter or systemd without code changes.
+///
+/// # Errors
+///
+/// Returns `ListenerError::ServerStarterPortEnvNotFound` if `LISTEN_FDS` or `LISTEN_PID` is not
+/// set, `ListenerError::ListenPidMismatch` if `LISTEN_PID` does not match the current process,
+/// and `ListenerError::InvalidServerStarterPortSpec` if `LISTEN_FDS` is not a valid integer.
+///
+pub fn listeners_from_env() -> Result<Listeners, ListenerError> {
+    let listen_pid = match std::env::var(LISTEN_PID_ENV) {
+        Ok(listen_pid) => listen_pid,
+        Err(_) => return Err(ListenerError::ServerStarterPortEnvNotFound),
+    };
+    let listen_pid: i32 = match listen_pid.parse() {
+        Ok(listen_pid) => listen_pid,
+        Err(_) => return Err(ListenerError::InvalidServerStarterPortSpec(listen_pid)),
+    };
+    if listen_pid != std::process::id() as i32 {
+        return Err(ListenerError::ListenPidMismatch(
+            listen_pid,
+            std::process::id(),
+        ));
+    }
+
+    let listen_fds = match std::env::var(LISTEN_FDS_ENV) {
+        Ok(listen_fds) => listen_fds,
+        Err(_) => return Err(ListenerError::ServerStarterPortEnvNotFound),
+    };
+    let listen_fds: u32 = match listen_fds.parse() {
+        Ok(listen_fds) => listen_fds,
+        Err(_) => return Err(ListenerError::InvalidServerStarterPortSpec(listen_fds)),
+    };
+
+    let fd_names = parse_fd_names(std::env::var(LISTEN_FDNAMES_ENV).ok().as_deref());
+
+    let mut results = vec![];
+    for offset in 0..listen_fds {
+        let fd = SD_LISTEN_FDS_START + offset as RawFd;
+        let name = fd_names.get(offset as usize).cloned().flatten();
+        results.push(ServerStarterListener::from_systemd_fd(fd, name)?);
+    }
+    Ok(Listeners(results))
+}
+
+///
+/// Kind of server starter listener, as a `tokio` async listener.
+///
+/// Mirrors [ServerStarterListener] one-to-one; see [ServerStarterListener::into_tokio].
+///
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub enum TokioServerStarterListener {
+    Tcp {
+        listener: tokio::net::TcpListener,
+        host: Option<String>,
+        port: u16,
+        name: Option<String>,
+    },
+    Uds {
+        listener: tokio::net::UnixListener,
+        path: String,
+        is_abstract: bool,
+        name: Option<String>,
+    },
+}
+
+#[cfg(feature = "tokio")]
+impl ServerStarterListener {
+    ///
+    /// Converts this listener into its `tokio` equivalent.
+    ///
+    /// Sets the inherited fd to non-blocking mode, as required by
+    /// `tokio::net::TcpListener::from_std` / `UnixListener::from_std`.
+    ///
+    pub fn into_tokio(self) -> Result<TokioServerStarterListener, ListenerError> {
+        match self {
+            ServerStarterListener::Tcp {
+                listener,
+                host,
+                port,
+                name,
+            } => {
+                listener
+                    .set_nonblocking(true)
+                    .map_err(ListenerError::TokioConversionError)?;
+                let listener = tokio::net::TcpListener::from_std(listener)
+                    .map_err(ListenerError::TokioConversionError)?;
+                Ok(TokioServerStarterListener::Tcp {
+                    listener,
+                    host,
+                    port,
+                    name,
+                })
+            }
+            ServerStarterListener::Uds {
+                listener,
+                path,
+                is_abstract,
+                name,
+            } => {
+                listener
+                    .set_nonblocking(true)
+                    .map_err(ListenerError::TokioConversionError)?;
+                let listener = tokio::net::UnixListener::from_std(listener)
+                    .map_err(ListenerError::TokioConversionError)?;
+                Ok(TokioServerStarterListener::Uds {
+                    listener,
+                    path,
+                    is_abstract,
+                    name,
+                })
+            }
+        }
+    }
+}
+
+///
+/// Get server starter listening listeners as `tokio` async listeners.
+///
+/// Equivalent to calling [ServerStarterListener::into_tokio] on every listener returned by
+/// [listeners].
+///
+#[cfg(feature = "tokio")]
+pub fn tokio_listeners() -> Result<Vec<TokioServerStarterListener>, ListenerError> {
+    listeners()?
+        .into_iter()
+        .map(ServerStarterListener::into_tokio)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+    use std::sync::Mutex;
 
-    use crate::{listeners, ServerStarterListener};
+    use crate::{
+        listeners, listeners_from_env, parse_fd_names, ServerStarterListener, SD_LISTEN_FDS_START,
+    };
+    #[cfg(feature = "tokio")]
+    use crate::TokioServerStarterListener;
+
+    /// Tests below mutate process-wide env vars and, in several cases, hand out low-numbered
+    /// file descriptors that can alias the systemd fd (`SD_LISTEN_FDS_START`) that another
+    /// test `dup2`s onto. Serialize them so they don't race on that shared process state.
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_env() -> std::sync::MutexGuard<'static, ()> {
+        TEST_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Binds a throwaway TCP listener purely to hand out a real, valid file descriptor for a
+    /// `SERVER_STARTER_PORT` spec under test; `listeners()` never inspects the fd's actual
+    /// socket address, only the spec text, so the bound address itself is irrelevant.
+    fn fresh_fd() -> RawFd {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .into_raw_fd()
+    }
 
     #[test]
     fn listeners_tcp() {
-        let assert_tcp_listener = |var, fd| {
-            std::env::set_var("SERVER_STARTER_PORT", var);
+        let _guard = lock_env();
+        let assert_tcp_listener = |host_port: &str, expected_host: Option<&str>, expected_port| {
+            let fd = fresh_fd();
+            std::env::set_var("SERVER_STARTER_PORT", format!("{}={}", host_port, fd));
             let results = listeners();
             match results {
                 Ok(results) => {
                     assert_eq!(1, results.len());
                     let listener = results.first().unwrap();
                     match listener {
-                        ServerStarterListener::Tcp(tcp_listener) => {
+                        ServerStarterListener::Tcp {
+                            listener: tcp_listener,
+                            host,
+                            port,
+                            ..
+                        } => {
                             assert_eq!(fd, tcp_listener.as_raw_fd());
+                            assert_eq!(expected_host, host.as_deref());
+                            assert_eq!(expected_port, *port);
                         }
-                        ServerStarterListener::Uds(_) => {
-                            assert!(false, "not tcp listener {:?}", listener)
+                        ServerStarterListener::Uds { .. } => {
+                            unreachable!("not tcp listener {:?}", listener)
                         }
                     }
                 }
-                Err(_) => assert!(false, "results not ok {:?}", results),
+                Err(_) => unreachable!("results not ok {:?}", results),
             }
         };
 
-        assert_tcp_listener("80=2", 2);
-        assert_tcp_listener("127.0.0.1:8080=3", 3);
-        assert_tcp_listener("localhost:8080=4", 4);
+        assert_tcp_listener("80", None, 80);
+        assert_tcp_listener("127.0.0.1:8080", Some("127.0.0.1"), 8080);
+        assert_tcp_listener("localhost:8080", Some("localhost"), 8080);
     }
 
     #[test]
     fn listeners_uds() {
-        let assert_uds_listener = |var, fd| {
-            std::env::set_var("SERVER_STARTER_PORT", var);
+        let _guard = lock_env();
+        let assert_uds_listener = |path_spec: &str, expected_path: &str, expected_abstract| {
+            let fd = fresh_fd();
+            std::env::set_var("SERVER_STARTER_PORT", format!("{}={}", path_spec, fd));
             let results = listeners();
             match results {
                 Ok(results) => {
                     assert_eq!(1, results.len());
                     let listener = results.first().unwrap();
                     match listener {
-                        ServerStarterListener::Tcp(_) => {
-                            assert!(false, "not uds listener {:?}", listener)
+                        ServerStarterListener::Tcp { .. } => {
+                            unreachable!("not uds listener {:?}", listener)
                         }
-                        ServerStarterListener::Uds(uds_listener) => {
+                        ServerStarterListener::Uds {
+                            listener: uds_listener,
+                            path,
+                            is_abstract,
+                            ..
+                        } => {
                             assert_eq!(fd, uds_listener.as_raw_fd());
+                            assert_eq!(expected_path, path);
+                            assert_eq!(expected_abstract, *is_abstract);
                         }
                     }
                 }
-                Err(_) => assert!(false, "results not ok {:?}", results),
+                Err(_) => unreachable!("results not ok {:?}", results),
             }
         };
 
-        assert_uds_listener("/tmp/server-starter-listener/server.sock=2", 2);
+        assert_uds_listener(
+            "/tmp/server-starter-listener/server.sock",
+            "/tmp/server-starter-listener/server.sock",
+            false,
+        );
+        assert_uds_listener("\\x00sccache.socket", "\0sccache.socket", true);
+        assert_uds_listener("/tmp/weird\\tname\\n", "/tmp/weird\tname\n", false);
+    }
+
+    #[test]
+    fn listeners_get_by_port_and_path() {
+        let _guard = lock_env();
+        let (tcp_fd, uds_fd) = (fresh_fd(), fresh_fd());
+        std::env::set_var(
+            "SERVER_STARTER_PORT",
+            format!(
+                "8080={};/tmp/server-starter-listener/server.sock={}",
+                tcp_fd, uds_fd
+            ),
+        );
+        let results = listeners().unwrap();
+        assert!(results.get_by_port(8080).is_some());
+        assert!(results.get_by_port(9090).is_none());
+        assert!(results
+            .get_by_path("/tmp/server-starter-listener/server.sock")
+            .is_some());
+        assert!(results.get_by_path("/tmp/does-not-exist.sock").is_none());
+    }
+
+    #[test]
+    fn listeners_invalid_abstract_socket_spec() {
+        let _guard = lock_env();
+        let fd = fresh_fd();
+        std::env::set_var("SERVER_STARTER_PORT", format!("\\xzzsccache.socket={}", fd));
+        assert!(listeners().is_err());
     }
 
     #[test]
     fn listeners_without_env() {
+        let _guard = lock_env();
         std::env::remove_var("SERVER_STARTER_PORT");
         assert!(listeners().is_err());
     }
 
     #[test]
     fn listeners_invalid_env() {
+        let _guard = lock_env();
         std::env::set_var("SERVER_STARTER_PORT", "80=a");
         assert!(listeners().is_err());
     }
+
+    #[test]
+    fn listeners_from_env_without_env() {
+        let _guard = lock_env();
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        assert!(listeners_from_env().is_err());
+    }
+
+    #[test]
+    fn listeners_from_env_pid_mismatch() {
+        let _guard = lock_env();
+        std::env::set_var("LISTEN_PID", "1");
+        std::env::set_var("LISTEN_FDS", "1");
+        match listeners_from_env() {
+            Err(crate::ListenerError::ListenPidMismatch(1, _)) => {}
+            other => unreachable!("expected ListenPidMismatch, got {:?}", other),
+        }
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+    }
+
+    #[test]
+    fn listeners_from_env_success_without_fds() {
+        let _guard = lock_env();
+        std::env::set_var("LISTEN_PID", std::process::id().to_string());
+        std::env::set_var("LISTEN_FDS", "0");
+        std::env::remove_var("LISTEN_FDNAMES");
+        let results = listeners_from_env().unwrap();
+        assert_eq!(0, results.len());
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+    }
+
+    #[test]
+    fn listeners_from_env_success_with_fds() {
+        let _guard = lock_env();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let expected_port = listener.local_addr().unwrap().port();
+        let fd = listener.into_raw_fd();
+        let dup_fd = unsafe { libc::dup2(fd, SD_LISTEN_FDS_START) };
+        assert_eq!(SD_LISTEN_FDS_START, dup_fd);
+        if fd != SD_LISTEN_FDS_START {
+            unsafe { libc::close(fd) };
+        }
+
+        std::env::set_var("LISTEN_PID", std::process::id().to_string());
+        std::env::set_var("LISTEN_FDS", "1");
+        std::env::set_var("LISTEN_FDNAMES", "web");
+        let results = listeners_from_env().unwrap();
+        assert_eq!(1, results.len());
+        let listener = results.first().unwrap();
+        match listener {
+            ServerStarterListener::Tcp { port, name, .. } => {
+                assert_eq!(expected_port, *port);
+                assert_eq!(Some("web"), name.as_deref());
+            }
+            ServerStarterListener::Uds { .. } => {
+                unreachable!("not tcp listener {:?}", listener)
+            }
+        }
+
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+    }
+
+    #[test]
+    fn from_systemd_fd_tcp() {
+        let _guard = lock_env();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let expected_port = listener.local_addr().unwrap().port();
+        let fd = listener.into_raw_fd();
+
+        let result = ServerStarterListener::from_systemd_fd(fd, Some("web".into())).unwrap();
+        match result {
+            ServerStarterListener::Tcp {
+                port, host, name, ..
+            } => {
+                assert_eq!(expected_port, port);
+                assert_eq!(None, host);
+                assert_eq!(Some("web".to_string()), name);
+            }
+            ServerStarterListener::Uds { .. } => {
+                unreachable!("not tcp listener {:?}", result)
+            }
+        }
+    }
+
+    #[test]
+    fn from_systemd_fd_uds() {
+        let _guard = lock_env();
+        let path = std::env::temp_dir().join(format!(
+            "server-starter-listener-systemd-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let fd = listener.into_raw_fd();
+
+        let result = ServerStarterListener::from_systemd_fd(fd, None).unwrap();
+        match &result {
+            ServerStarterListener::Uds {
+                path: actual_path,
+                is_abstract,
+                ..
+            } => {
+                assert_eq!(&path.to_string_lossy(), actual_path);
+                assert!(!*is_abstract);
+            }
+            ServerStarterListener::Tcp { .. } => {
+                unreachable!("not uds listener {:?}", result)
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn from_systemd_fd_uds_abstract() {
+        let _guard = lock_env();
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let addr = SocketAddr::from_abstract_name(b"sccache.socket").unwrap();
+        let listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+        let fd = listener.into_raw_fd();
+
+        let result = ServerStarterListener::from_systemd_fd(fd, None).unwrap();
+        match &result {
+            ServerStarterListener::Uds {
+                path, is_abstract, ..
+            } => {
+                assert_eq!("\0sccache.socket", path);
+                assert!(*is_abstract);
+            }
+            ServerStarterListener::Tcp { .. } => {
+                unreachable!("not uds listener {:?}", result)
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn into_tokio_tcp() {
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let expected_port = std_listener.local_addr().unwrap().port();
+        let listener =
+            ServerStarterListener::from_tcp(std_listener, None, expected_port, Some("web".into()));
+
+        let tokio_listener = listener.into_tokio().unwrap();
+        match tokio_listener {
+            TokioServerStarterListener::Tcp {
+                listener,
+                port,
+                name,
+                ..
+            } => {
+                assert_eq!(expected_port, port);
+                assert_eq!(Some("web".to_string()), name);
+                assert_eq!(expected_port, listener.local_addr().unwrap().port());
+            }
+            TokioServerStarterListener::Uds { .. } => {
+                unreachable!("not tcp listener")
+            }
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn into_tokio_uds() {
+        let path = std::env::temp_dir().join(format!(
+            "server-starter-listener-tokio-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let std_listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let listener = ServerStarterListener::from_uds(
+            std_listener,
+            path.to_string_lossy().into_owned(),
+            false,
+            None,
+        )
+        .unwrap();
+
+        let tokio_listener = listener.into_tokio().unwrap();
+        match tokio_listener {
+            TokioServerStarterListener::Uds {
+                is_abstract, path: p, ..
+            } => {
+                assert_eq!(path.to_string_lossy(), p);
+                assert!(!is_abstract);
+            }
+            TokioServerStarterListener::Tcp { .. } => {
+                unreachable!("not uds listener")
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn parse_fd_names_splits_colon_separated() {
+        assert_eq!(
+            vec![Some("http".to_string()), Some("https".to_string())],
+            parse_fd_names(Some("http:https"))
+        );
+        assert_eq!(Vec::<Option<String>>::new(), parse_fd_names(Some("")));
+        assert_eq!(Vec::<Option<String>>::new(), parse_fd_names(None));
+    }
 }